@@ -0,0 +1,163 @@
+//! User config loaded from `~/.config/hwm/config.yaml`, merged over the defaults below.
+use crate::{BAR_HEIGHT_PX, BLACK, BLUE, FONT, GREY, INNER_PX, OUTER_PX, RATIO, WHITE};
+use penrose::{
+    core::{hooks::ManageHook, State},
+    extensions::hooks::manage::{FloatingCentered, SetWorkspace},
+    x::{query::ClassName, Query, XConn},
+    Result, Xid,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Colors {
+    pub black: u32,
+    pub white: u32,
+    pub grey: u32,
+    pub blue: u32,
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        Self {
+            black: BLACK,
+            white: WHITE,
+            grey: GREY,
+            blue: BLUE,
+        }
+    }
+}
+
+/// A single `manage_hook` rule, keyed by window class in [`UserConfig::float_rules`].
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FloatRule {
+    FloatingCentered { w: f32, h: f32 },
+    SetWorkspace { tag: String },
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UserConfig {
+    pub font: String,
+    pub colors: Colors,
+    pub ratio: f32,
+    pub outer_px: u32,
+    pub inner_px: u32,
+    pub bar_height_px: u32,
+    pub spawn_bindings: HashMap<String, String>,
+    pub float_rules: HashMap<String, FloatRule>,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        Self {
+            font: FONT.to_owned(),
+            colors: Colors::default(),
+            ratio: RATIO,
+            outer_px: OUTER_PX,
+            inner_px: INNER_PX,
+            bar_height_px: BAR_HEIGHT_PX,
+            spawn_bindings: default_spawn_bindings(),
+            float_rules: default_float_rules(),
+        }
+    }
+}
+
+fn default_spawn_bindings() -> HashMap<String, String> {
+    [
+        ("M-Print", "screenshot_menu"),
+        ("M-S-Print", "screenshot_menu -s"),
+        ("M-S-f", "st -e lf"),
+        ("M-c", "CM_LAUNCHER=rofi clipmenu"),
+        ("M-w", "qutebrowser"),
+        ("M-b", "bluethooth_menu"),
+        ("M-m", "st -e termusic"),
+        ("M-a", "rofi-pass"),
+        ("M-n", "st -e news"),
+        ("M-S-t", "st -e btop"),
+        ("M-S-x", "xrandr_menu"),
+        ("M-t", "term_menu"),
+        ("M-period", "rofimenu"),
+        ("M-S-period", "nerdfont_menu"),
+        ("M-semicolon", "rofi -show run"),
+        ("M-S-q", "exit_menu"),
+        ("M-d", "rofi -show run"),
+        ("M-Return", "st"),
+        ("M-A-w", "floating-webcam"),
+        ("M-A-l", "xflock4"),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_owned(), v.to_owned()))
+    .collect()
+}
+
+fn default_float_rules() -> HashMap<String, FloatRule> {
+    let centered = FloatRule::FloatingCentered { w: 0.8, h: 0.6 };
+
+    [
+        ("floatTerm", centered.clone()),
+        ("Xnest", centered.clone()),
+        ("copyq", centered.clone()),
+        ("dmenu", centered.clone()),
+        ("dunst", centered.clone()),
+        ("onboard", centered.clone()),
+        ("pinentry-gtk-2", centered.clone()),
+        ("polybar", centered),
+        (
+            "rofi",
+            FloatRule::SetWorkspace {
+                tag: "9".to_owned(),
+            },
+        ),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_owned(), v))
+    .collect()
+}
+
+/// Load `~/.config/hwm/config.yaml`, falling back to [`UserConfig::default`] on any error.
+pub fn load_user_config() -> UserConfig {
+    let Some(path) = dirs::config_dir().map(|d| d.join("hwm").join("config.yaml")) else {
+        return UserConfig::default();
+    };
+
+    let raw = match std::fs::read_to_string(&path) {
+        Ok(raw) => raw,
+        Err(_) => return UserConfig::default(),
+    };
+
+    match serde_yaml::from_str(&raw) {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            tracing::warn!(
+                "failed to parse {}: {e}, falling back to built-in defaults",
+                path.display()
+            );
+            UserConfig::default()
+        }
+    }
+}
+
+/// A `manage_hook` that looks a window's class up in a [`FloatRule`] map.
+pub struct ClassRules(pub HashMap<String, FloatRule>);
+
+impl<X: XConn> ManageHook<X> for ClassRules {
+    fn call(&mut self, id: Xid, state: &mut State<X>, x: &X) -> Result<()> {
+        for (class, rule) in self.0.iter() {
+            if !ClassName(class).check(id, state, x)? {
+                continue;
+            }
+
+            return match rule {
+                FloatRule::FloatingCentered { w, h } => {
+                    FloatingCentered::new(*w, *h).call(id, state, x)
+                }
+                FloatRule::SetWorkspace { tag } => SetWorkspace(tag.clone()).call(id, state, x),
+            };
+        }
+
+        Ok(())
+    }
+}
@@ -30,22 +30,36 @@ use penrose::{
     extensions::{
         hooks::{
             add_ewmh_hooks, add_named_scratchpads,
-            manage::{FloatingCentered, SetWorkspace},
+            manage::FloatingCentered,
             NamedScratchPad, SpawnOnStartup, ToggleNamedScratchPad,
         },
         layout::{Conditional, Fibonacci, Tatami},
     },
-    manage_hooks, map, stack,
+    map, stack,
     x::{query::ClassName, XConn, XConnExt},
     x11rb::RustConn,
-    Xid,
+    Result, Xid,
 };
-use std::collections::HashMap;
+use penrose_ui::{
+    bar::{
+        widgets::{ActiveWindowName, CurrentLayout, Widget, Workspaces},
+        Position, StatusBar,
+    },
+    core::TextStyle,
+};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::process::{Command, Stdio};
 use tracing_subscriber::{self, reload::Handle, EnvFilter};
 
+mod config;
+
+use config::{load_user_config, ClassRules, UserConfig};
+
 pub type KeyHandler = Box<dyn KeyEventHandler<RustConn>>;
 
 pub const FONT: &str = "FiraCode Nerd Font Mono";
+pub const POINT_SIZE: u8 = 11;
 pub const BLACK: u32 = 0x282828ff;
 pub const WHITE: u32 = 0xebdbb2ff;
 pub const GREY: u32 = 0x3c3836ff;
@@ -74,35 +88,71 @@ where
     X: XConn + 'static,
 {
     wm.state.add_extension(StickyClientState(Vec::new()));
-    // wm.state.config.compose_or_set_refresh_hook(refresh_hook);
+    wm.state.config.compose_or_set_refresh_hook(refresh_hook);
 
     wm
 }
 
-// fn refresh_hook<X: XConn>(state: &mut State<X>, x: &X) -> Result<()> {
-//     let s = state.extension::<StickyClientState>()?;
-//     let t = state.client_set.current_tag().to_string();
-//     let mut need_refresh = false;
-//
-//     // clear out any clients we were tracking that are no longer in state
-//     s.borrow_mut().0.retain(|id| state.client_set.contains(id));
-//
-//     for client in s.borrow().0.iter() {
-//         if state.client_set.tag_for_client(client) != Some(&t) {
-//             state.client_set.move_client_to_tag(client, &t);
-//             need_refresh = true;
-//         }
-//     }
-//
-//     // we guard against refreshing only when clients were on the wrong screen
-//     // so that we don't get into an infinite loop from calling refresh from
-//     // inside of a refresh hook
-//     if need_refresh {
-//         x.refresh(state)?;
-//     }
-//
-//     Ok(())
-// }
+fn refresh_hook<X: XConn>(state: &mut State<X>, x: &X) -> Result<()> {
+    let s = state.extension::<StickyClientState>()?;
+    let t = state.client_set.current_tag().to_string();
+    let mut need_refresh = false;
+
+    // clear out any clients we were tracking that are no longer in state
+    s.borrow_mut().0.retain(|id| state.client_set.contains(id));
+
+    for client in s.borrow().0.iter() {
+        if state.client_set.tag_for_client(client) != Some(&t) {
+            state.client_set.move_client_to_tag(client, &t);
+            need_refresh = true;
+        }
+    }
+
+    // we guard against refreshing only when clients were on the wrong screen
+    // so that we don't get into an infinite loop from calling refresh from
+    // inside of a refresh hook
+    if need_refresh {
+        x.refresh(state)?;
+    }
+
+    Ok(())
+}
+
+// Tags already focused once, whether or not `per_tag_layouts` has an override for them
+struct AppliedTagLayouts(HashSet<String>);
+
+// Swap a tag's layout stack the first time it's focused
+pub fn add_per_tag_layouts<X>(
+    mut wm: WindowManager<X>,
+    layouts: HashMap<String, LayoutStack>,
+) -> WindowManager<X>
+where
+    X: XConn + 'static,
+{
+    wm.state.add_extension(AppliedTagLayouts(HashSet::new()));
+    wm.state
+        .config
+        .compose_or_set_refresh_hook(move |state: &mut State<X>, _: &X| {
+            let tag = state.client_set.current_tag().to_string();
+            let applied = state.extension::<AppliedTagLayouts>()?;
+
+            if applied.borrow().0.contains(&tag) {
+                return Ok(());
+            }
+
+            if let Some(stack) = layouts.get(&tag) {
+                if let Some(ws) = state.client_set.workspace_mut(&tag) {
+                    ws.layouts = stack.clone();
+                }
+            }
+
+            applied.borrow_mut().0.insert(tag);
+
+            Ok(())
+        });
+
+    wm
+}
 
 pub fn toggle_sticky_client() -> KeyHandler {
     key_handler(|state, x: &RustConn| {
@@ -124,8 +174,83 @@ pub fn toggle_sticky_client() -> KeyHandler {
     })
 }
 
+// Jump to a window by title, across all tags, via rofi
+pub fn goto_window() -> KeyHandler {
+    key_handler(|state, x: &RustConn| {
+        let mut entries = Vec::new();
+        for tag in state.client_set.tags() {
+            for &id in state.client_set.clients_for_tag(tag) {
+                let name = x.client_name(id).unwrap_or_else(|_| "<unknown>".to_owned());
+                entries.push((tag.to_string(), id, name));
+            }
+        }
+
+        let lines: Vec<String> = entries
+            .iter()
+            .map(|(tag, _, name)| format!("{tag}: {name}"))
+            .collect();
+
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        let mut rofi = Command::new("rofi")
+            .args(["-dmenu", "-i", "-p", "window", "-format", "i"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        rofi.stdin
+            .take()
+            .expect("stdin was piped")
+            .write_all(lines.join("\n").as_bytes())?;
+
+        let output = rofi.wait_with_output()?;
+        let choice = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+        if let Ok(ix) = choice.parse::<usize>() {
+            if let Some((tag, id, _)) = entries.get(ix) {
+                state.client_set.pull_tag_to_screen(tag);
+                state.client_set.focus_client(id);
+                x.refresh(state)?;
+            }
+        }
+
+        Ok(())
+    })
+}
+
+// Relative volume step via amixer
+pub fn volume(delta: i32) -> KeyHandler {
+    key_handler(move |_, _: &RustConn| {
+        let step = format!("{}%{}", delta.abs(), if delta >= 0 { "+" } else { "-" });
+        Command::new("amixer").args(["set", "Master", &step]).status()?;
+
+        Ok(())
+    })
+}
+
+pub fn toggle_mute() -> KeyHandler {
+    key_handler(|_, _: &RustConn| {
+        Command::new("amixer")
+            .args(["set", "Master", "toggle"])
+            .status()?;
+
+        Ok(())
+    })
+}
+
+pub fn mpd_cmd(cmd: &'static str) -> KeyHandler {
+    key_handler(move |_, _: &RustConn| {
+        Command::new("mpc").arg(cmd).status()?;
+
+        Ok(())
+    })
+}
+
 // Generate a raw key binding map in terms of parsable string key bindings rather than resolved key codes
 pub fn raw_key_bindings<L, S>(
+    cfg: &UserConfig,
     toggle_scratch: ToggleNamedScratchPad,
     toggle_scratch_py: ToggleNamedScratchPad,
     handle: Handle<L, S>,
@@ -162,30 +287,14 @@ where
         "M-S-Left" => send_layout_message(|| ShrinkMain),
 
         // Launchers
-        "M-Print" => spawn("screenshot_menu"),
-        "M-S-Print" => spawn("screenshot_menu -s"),
-        "M-S-f" => spawn("st -e lf"),
-        "M-c" => spawn("CM_LAUNCHER=rofi clipmenu"),
-        "M-w" => spawn("qutebrowser"),
-        "M-b" => spawn("bluethooth_menu"),
-        "M-m" => spawn("st -e termusic"),
-        "M-a" => spawn("rofi-pass"),
-        "M-n" => spawn("st -e news"),
-        "M-S-t" => spawn("st -e btop"),
-        "M-S-x" => spawn("xrandr_menu"),
-        "M-t" => spawn("term_menu"),
-        "M-period" => spawn("rofimenu"),
-        "M-S-period" => spawn("nerdfont_menu"),
-        "M-semicolon" => spawn("rofi -show run"),
-        "M-S-q" => spawn("exit_menu"),
-        "M-d" => spawn("rofi -show run"),
-        "M-Return" => spawn("st"),
-        "M-A-w" => spawn("floating-webcam"),
+        // NOTE: the actual commands come from `cfg.spawn_bindings` below, so they can be
+        // remapped from `hwm/config.yaml`. A `spawn_bindings` map given there replaces the
+        // compiled-in default map as a whole; it is not merged key by key.
+        "M-slash" => goto_window(),
         "M-S-Return" => Box::new(toggle_scratch),
         "M-C-Return" => Box::new(toggle_scratch_py),
 
         // Session management
-        "M-A-l" => spawn("xflock4"),
         // "M-A-Escape" => power_menu(),
 
         "M-C-t" => toggle_sticky_client(),
@@ -208,8 +317,20 @@ where
         // Debugging
         // "M-A-t" => set_tracing_filter(handle),
         "M-A-d" => log_current_state(),
+
+        // Media keys: volume and MPD transport for the termusic setup launched by `M-m`.
+        "XF86AudioRaiseVolume" => volume(5),
+        "XF86AudioLowerVolume" => volume(-5),
+        "XF86AudioMute" => toggle_mute(),
+        "XF86AudioPlay" => mpd_cmd("toggle"),
+        "XF86AudioNext" => mpd_cmd("next"),
+        "XF86AudioPrev" => mpd_cmd("prev"),
     };
 
+    for (key, cmd) in cfg.spawn_bindings.iter() {
+        raw_bindings.insert(key.clone(), spawn(cmd.clone()));
+    }
+
     for tag in &["1", "2", "3", "4", "5", "6", "7", "8", "9"] {
         raw_bindings.extend([
             (
@@ -226,39 +347,93 @@ where
     raw_bindings
 }
 
-fn layouts() -> LayoutStack {
+fn layouts(cfg: &UserConfig) -> LayoutStack {
+    let ratio = cfg.ratio;
+
     stack!(
-        flex_tall(),
-        flex_wide(),
-        MainAndStack::side(MAX_MAIN, RATIO, RATIO_STEP),
-        ReflectHorizontal::wrap(MainAndStack::side(MAX_MAIN, RATIO, RATIO_STEP)),
-        MainAndStack::bottom(MAX_MAIN, RATIO, RATIO_STEP),
-        Tatami::boxed(RATIO, RATIO_STEP),
-        Fibonacci::boxed(MAX_MAIN, RATIO, RATIO_STEP),
+        flex_tall(cfg),
+        flex_wide(cfg),
+        MainAndStack::side(MAX_MAIN, ratio, RATIO_STEP),
+        ReflectHorizontal::wrap(MainAndStack::side(MAX_MAIN, ratio, RATIO_STEP)),
+        MainAndStack::bottom(MAX_MAIN, ratio, RATIO_STEP),
+        Tatami::boxed(ratio, RATIO_STEP),
+        Fibonacci::boxed(MAX_MAIN, ratio, RATIO_STEP),
         Grid::boxed(),
         Monocle::boxed()
     )
-    .map(|layout| ReserveTop::wrap(Gaps::wrap(layout, OUTER_PX, INNER_PX), BAR_HEIGHT_PX))
+    .map(|layout| ReserveTop::wrap(Gaps::wrap(layout, cfg.outer_px, cfg.inner_px), cfg.bar_height_px))
+}
+
+// Layout overrides for specific tags; unlisted tags use `layouts()`
+fn per_tag_layouts(cfg: &UserConfig) -> HashMap<String, LayoutStack> {
+    let spaced = |layout: Box<dyn Layout>| {
+        ReserveTop::wrap(Gaps::wrap(layout, cfg.outer_px, cfg.inner_px), cfg.bar_height_px)
+    };
+
+    map! {
+        map_keys: |k: &str| k.to_owned();
+
+        // `rofi` lands here via `SetWorkspace("9")`: Monocle keeps it full-screen
+        // rather than competing for tiled space.
+        "9" => stack!(spaced(Monocle::boxed())),
+        // Media tag for termusic (`M-m`): a centred main pane with the other clients split to either side.
+        "5" => stack!(spaced(CenteredMain::boxed(MAX_MAIN, cfg.ratio, RATIO_STEP))),
+    }
 }
 
-fn flex_tall() -> Box<dyn Layout> {
+fn flex_tall(cfg: &UserConfig) -> Box<dyn Layout> {
     Conditional::boxed(
         "FlexTall",
-        MainAndStack::side_unboxed(MAX_MAIN, RATIO, RATIO_STEP, false),
-        CenteredMain::vertical_unboxed(MAX_MAIN, RATIO, RATIO_STEP),
+        MainAndStack::side_unboxed(MAX_MAIN, cfg.ratio, RATIO_STEP, false),
+        CenteredMain::vertical_unboxed(MAX_MAIN, cfg.ratio, RATIO_STEP),
         |_, r| r.w <= 1400,
     )
 }
 
-fn flex_wide() -> Box<dyn Layout> {
+fn flex_wide(cfg: &UserConfig) -> Box<dyn Layout> {
     Conditional::boxed(
         "FlexWide",
-        MainAndStack::bottom_unboxed(MAX_MAIN, RATIO, RATIO_STEP, false),
-        CenteredMain::horizontal_unboxed(MAX_MAIN, RATIO, RATIO_STEP),
+        MainAndStack::bottom_unboxed(MAX_MAIN, cfg.ratio, RATIO_STEP, false),
+        CenteredMain::horizontal_unboxed(MAX_MAIN, cfg.ratio, RATIO_STEP),
         |_, r| r.w <= 1400,
     )
 }
 
+// Built-in status bar, drawn in the reserved `bar_height_px` strip
+fn status_bar(cfg: &UserConfig) -> Result<StatusBar<RustConn>> {
+    let style = TextStyle {
+        font: cfg.font.clone(),
+        point_size: POINT_SIZE,
+        fg: cfg.colors.white.into(),
+        bg: Some(cfg.colors.black.into()),
+        padding: (2, 2),
+    };
+
+    let widgets: Vec<Box<dyn Widget<RustConn>>> = vec![
+        Box::new(Workspaces::new(
+            style.clone(),
+            cfg.colors.blue.into(),
+            cfg.colors.grey.into(),
+        )),
+        Box::new(CurrentLayout::new(style.clone())),
+        Box::new(ActiveWindowName::new(
+            style.clone(),
+            MAX_ACTIVE_WINDOW_CHARS,
+            true,
+            false,
+        )),
+    ];
+
+    StatusBar::try_new(
+        Position::Top,
+        cfg.bar_height_px,
+        style,
+        cfg.colors.blue.into(),
+        cfg.colors.grey.into(),
+        widgets,
+    )
+}
+
 fn main() -> anyhow::Result<()> {
     // NOTE: Setting up tracing with dynamic filter updating inline as getting the type for
     // the reload Handle to work is a massive pain... this really should be in its own method
@@ -274,28 +449,19 @@ fn main() -> anyhow::Result<()> {
     let reload_handle = tracing_builder.reload_handle();
     // tracing_builder.finish().init();
 
+    let user_cfg = load_user_config();
+
     let startup_hook = SpawnOnStartup::boxed("/usr/local/scripts/penrose-startup.sh");
-    let manage_hook = manage_hooks![
-        ClassName("floatTerm") => FloatingCentered::new(0.8, 0.6),
-        ClassName("Xnest") => FloatingCentered::new(0.8, 0.6),
-        ClassName("copyq") => FloatingCentered::new(0.8, 0.6),
-        ClassName("dmenu") => FloatingCentered::new(0.8, 0.6),
-        ClassName("dunst") => FloatingCentered::new(0.8, 0.6),
-        ClassName("onboard") => FloatingCentered::new(0.8, 0.6),
-        ClassName("pinentry-gtk-2") => FloatingCentered::new(0.8, 0.6),
-        ClassName("polybar") => FloatingCentered::new(0.8, 0.6),
-        ClassName("floatTerm") => FloatingCentered::new(0.8, 0.6),
-        ClassName("rofi")  => SetWorkspace("9"),
-    ];
+    let manage_hook = Box::new(ClassRules(user_cfg.float_rules.clone()));
     let layout_hook = SpacingHook {
-        inner_px: INNER_PX,
-        outer_px: OUTER_PX,
-        top_px: BAR_HEIGHT_PX,
+        inner_px: user_cfg.inner_px,
+        outer_px: user_cfg.outer_px,
+        top_px: user_cfg.bar_height_px,
         bottom_px: 0,
     };
 
     let config = add_ewmh_hooks(Config {
-        default_layouts: layouts(),
+        default_layouts: layouts(&user_cfg),
         floating_classes: vec!["mpv-float".to_owned()],
         manage_hook: Some(manage_hook),
         startup_hook: Some(startup_hook),
@@ -303,6 +469,9 @@ fn main() -> anyhow::Result<()> {
         ..Config::default()
     });
 
+    // Internal status bar, composed on top of the EWMH hooks above
+    let config = status_bar(&user_cfg)?.add_to_config(config);
+
     // Create a new named scratchpad and toggle handle for use in keybindings.
     let (nsp, toggle_scratch) = NamedScratchPad::new(
         "terminal",
@@ -321,14 +490,17 @@ fn main() -> anyhow::Result<()> {
     );
 
     let conn = RustConn::new()?;
-    let raw_bindings = raw_key_bindings(toggle_scratch, toggle_scratch_py, reload_handle);
+    let raw_bindings = raw_key_bindings(&user_cfg, toggle_scratch, toggle_scratch_py, reload_handle);
     let key_bindings = parse_keybindings_with_xmodmap(raw_bindings)?;
 
     // Initialise the required state extension and hooks for handling the named scratchpad
-    let wm = add_sticky_client_state(add_named_scratchpads(
-        WindowManager::new(config, key_bindings, HashMap::new(), conn)?,
-        vec![nsp, nsp_py],
-    ));
+    let wm = add_per_tag_layouts(
+        add_sticky_client_state(add_named_scratchpads(
+            WindowManager::new(config, key_bindings, HashMap::new(), conn)?,
+            vec![nsp, nsp_py],
+        )),
+        per_tag_layouts(&user_cfg),
+    );
 
     wm.run()?;
 